@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::error::Error as StdError;
 use std::str::FromStr;
 
@@ -12,7 +13,7 @@ use syn::{
     Error, Expr, Item,
 };
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
 struct Version {
     major: usize,
     minor: usize,
@@ -44,12 +45,53 @@ impl Version {
 
 enum VersionReq {
     Exactly(Version),
+    AtLeast(Version),
+    AtMost(Version),
+    GreaterThan(Version),
+    LessThan(Version),
+    Range {
+        start: Version,
+        end: Version,
+        inclusive: bool,
+    },
 }
 
 impl FromStr for VersionReq {
     type Err = <Version as FromStr>::Err;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = s.strip_prefix(">=") {
+            return Ok(Self::AtLeast(rest.parse()?));
+        }
+
+        if let Some(rest) = s.strip_prefix("<=") {
+            return Ok(Self::AtMost(rest.parse()?));
+        }
+
+        if let Some(rest) = s.strip_prefix('>') {
+            return Ok(Self::GreaterThan(rest.parse()?));
+        }
+
+        if let Some(rest) = s.strip_prefix('<') {
+            return Ok(Self::LessThan(rest.parse()?));
+        }
+
+        if let Some((start, end)) = s.split_once("..=") {
+            return Ok(Self::Range {
+                start: start.parse()?,
+                end: end.parse()?,
+                inclusive: true,
+            });
+        }
+
+        if let Some((start, end)) = s.split_once("..") {
+            return Ok(Self::Range {
+                start: start.parse()?,
+                end: end.parse()?,
+                inclusive: false,
+            });
+        }
+
         Ok(Self::Exactly(s.parse()?))
     }
 }
@@ -58,28 +100,85 @@ impl VersionReq {
     fn matches(&self, version: &Version) -> bool {
         match self {
             VersionReq::Exactly(expected_version) => version == expected_version,
+            VersionReq::AtLeast(min) => version >= min,
+            VersionReq::AtMost(max) => version <= max,
+            VersionReq::GreaterThan(min) => version > min,
+            VersionReq::LessThan(max) => version < max,
+            VersionReq::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                version >= start
+                    && (if *inclusive {
+                        version <= end
+                    } else {
+                        version < end
+                    })
+            }
         }
     }
 }
 
+/// The contents of a single `#[versioned(..)]` attribute: the version
+/// requirement, plus an optional `rename = "..."` that retargets the
+/// annotated field or variant's `#[serde(rename = "..")]` for that version.
+struct VersionedAttrArgs {
+    version: syn::LitStr,
+    rename: Option<syn::LitStr>,
+}
+
+impl syn::parse::Parse for VersionedAttrArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let version: syn::LitStr = input.parse()?;
+        let mut rename = None;
+
+        if input.peek(Comma) {
+            input.parse::<Comma>()?;
+
+            let key = input.parse::<syn::Ident>()?;
+            if key != "rename" {
+                return Err(Error::new(key.span(), "expected `rename`"));
+            }
+
+            input.parse::<syn::Token![=]>()?;
+            rename = Some(input.parse()?);
+        }
+
+        Ok(Self { version, rename })
+    }
+}
+
+/// A single parsed `#[versioned(..)]` attribute, ready to be matched against
+/// the version currently being generated. An item can carry more than one of
+/// these; see `VersionFilter::is_present`.
+struct VersionedAttr {
+    requirement: VersionReq,
+    rename: Option<syn::LitStr>,
+}
+
 struct VersionFilter {
     version: Version,
     error: Option<Error>,
 }
 
 impl VersionFilter {
-    fn extract_requirement(&mut self, attrs: &mut Vec<syn::Attribute>) -> Option<VersionReq> {
-        let mut opt_version = None;
+    /// Parses and strips every `#[versioned(..)]` attribute out of `attrs`.
+    fn extract_versioned_attrs(&mut self, attrs: &mut Vec<syn::Attribute>) -> Vec<VersionedAttr> {
+        let mut parsed = Vec::new();
 
         attrs.retain(|attr| {
             let path = attr.path();
 
             if path.is_ident("versioned") {
-                match attr
-                    .parse_args::<syn::LitStr>()
-                    .and_then(|s| s.value().parse().map_err(|err| Error::new(s.span(), err)))
-                {
-                    Ok(version) => opt_version = Some(version),
+                match attr.parse_args::<VersionedAttrArgs>() {
+                    Ok(VersionedAttrArgs { version, rename }) => match version.value().parse() {
+                        Ok(requirement) => parsed.push(VersionedAttr {
+                            requirement,
+                            rename,
+                        }),
+                        Err(err) => self.error = Some(Error::new(version.span(), err)),
+                    },
                     Err(err) => self.error = Some(err),
                 }
 
@@ -89,25 +188,91 @@ impl VersionFilter {
             }
         });
 
-        opt_version
+        parsed
     }
 
     fn matches(&self, requirement: &VersionReq) -> bool {
         requirement.matches(&self.version)
     }
 
+    /// Whether an item carrying `attrs` should be kept for the version
+    /// currently being generated. With no `#[versioned(..)]` attributes at
+    /// all, the item is unconditionally kept. A `rename`-only attribute never
+    /// gates presence by itself -- it only controls the serialized name once
+    /// the item is otherwise kept -- so a field can be renamed partway
+    /// through a version range while staying present throughout. If any
+    /// plain (non-`rename`) attribute is present, the item is kept only when
+    /// at least one of them matches; multiple such attributes still OR
+    /// together.
+    fn is_present(&self, attrs: &[VersionedAttr]) -> bool {
+        let mut gating = attrs.iter().filter(|attr| attr.rename.is_none()).peekable();
+
+        if gating.peek().is_none() {
+            true
+        } else {
+            gating.any(|attr| self.matches(&attr.requirement))
+        }
+    }
+
+    /// The rename that applies for the version currently being generated, if
+    /// any of `attrs`' requirements match.
+    fn matching_rename(&self, attrs: &[VersionedAttr]) -> Option<syn::LitStr> {
+        attrs
+            .iter()
+            .filter(|attr| self.matches(&attr.requirement))
+            .find_map(|attr| attr.rename.clone())
+    }
+
+    /// Strips `attrs`' `#[versioned(..)]` attributes and reports whether the
+    /// item should be kept for the version currently being generated.
+    fn retain_if_present(&mut self, attrs: &mut Vec<syn::Attribute>) -> bool {
+        let parsed = self.extract_versioned_attrs(attrs);
+        self.is_present(&parsed)
+    }
+
     fn filter_fields(
         &mut self,
         fields: Punctuated<syn::Field, Comma>,
     ) -> Punctuated<syn::Field, Comma> {
         fields
             .into_pairs()
-            .filter_map(
-                |mut pair| match self.extract_requirement(&mut pair.value_mut().attrs) {
-                    Some(version) => self.matches(&version).then_some(pair),
-                    None => Some(pair),
-                },
-            )
+            .filter_map(|mut pair| {
+                let field = pair.value_mut();
+                let parsed = self.extract_versioned_attrs(&mut field.attrs);
+
+                if !self.is_present(&parsed) {
+                    return None;
+                }
+
+                if let Some(rename) = self.matching_rename(&parsed) {
+                    apply_rename(&mut field.attrs, rename);
+                }
+
+                Some(pair)
+            })
+            .collect()
+    }
+
+    fn filter_variants(
+        &mut self,
+        variants: Punctuated<syn::Variant, Comma>,
+    ) -> Punctuated<syn::Variant, Comma> {
+        variants
+            .into_pairs()
+            .filter_map(|mut pair| {
+                let variant = pair.value_mut();
+                let parsed = self.extract_versioned_attrs(&mut variant.attrs);
+
+                if !self.is_present(&parsed) {
+                    return None;
+                }
+
+                if let Some(rename) = self.matching_rename(&parsed) {
+                    apply_rename(&mut variant.attrs, rename);
+                }
+
+                Some(pair)
+            })
             .collect()
     }
 }
@@ -123,14 +288,17 @@ impl Fold for VersionFilter {
         fields
     }
 
+    fn fold_item_enum(&mut self, mut item: syn::ItemEnum) -> syn::ItemEnum {
+        item.variants = self.filter_variants(item.variants);
+        fold::fold_item_enum(self, item)
+    }
+
     fn fold_stmt(&mut self, mut stmt: syn::Stmt) -> syn::Stmt {
         match stmt {
             syn::Stmt::Local(syn::Local { ref mut attrs, .. })
             | syn::Stmt::Macro(syn::StmtMacro { ref mut attrs, .. }) => {
-                if let Some(version) = self.extract_requirement(attrs) {
-                    if !self.matches(&version) {
-                        stmt = parse_quote!({};);
-                    }
+                if !self.retain_if_present(attrs) {
+                    stmt = parse_quote!({};);
                 }
             }
             _ => {}
@@ -179,10 +347,8 @@ impl Fold for VersionFilter {
             | Expr::Unsafe(syn::ExprUnsafe { ref mut attrs, .. })
             | Expr::While(syn::ExprWhile { ref mut attrs, .. })
             | Expr::Yield(syn::ExprYield { ref mut attrs, .. }) => {
-                if let Some(version) = self.extract_requirement(attrs) {
-                    if !self.matches(&version) {
-                        expr = parse_quote!({});
-                    }
+                if !self.retain_if_present(attrs) {
+                    expr = parse_quote!({});
                 }
             }
             _ => {}
@@ -195,12 +361,10 @@ impl Fold for VersionFilter {
         expr.fields = expr
             .fields
             .into_pairs()
-            .filter_map(
-                |mut pair| match self.extract_requirement(&mut pair.value_mut().attrs) {
-                    Some(version) => self.matches(&version).then_some(pair),
-                    None => Some(pair),
-                },
-            )
+            .filter_map(|mut pair| {
+                self.retain_if_present(&mut pair.value_mut().attrs)
+                    .then_some(pair)
+            })
             .collect();
 
         fold::fold_expr_struct(self, expr)
@@ -208,10 +372,7 @@ impl Fold for VersionFilter {
 
     fn fold_expr_match(&mut self, mut expr: syn::ExprMatch) -> syn::ExprMatch {
         expr.arms
-            .retain_mut(|arm| match self.extract_requirement(&mut arm.attrs) {
-                Some(version) => self.matches(&version),
-                None => true,
-            });
+            .retain_mut(|arm| self.retain_if_present(&mut arm.attrs));
 
         fold::fold_expr_match(self, expr)
     }
@@ -233,12 +394,10 @@ impl Fold for VersionFilter {
             | Item::Type(syn::ItemType { ref mut attrs, .. })
             | Item::Union(syn::ItemUnion { ref mut attrs, .. })
             | Item::Use(syn::ItemUse { ref mut attrs, .. }) => {
-                if let Some(version) = self.extract_requirement(attrs) {
-                    if !self.matches(&version) {
-                        item = parse_quote!(
-                            use {};
-                        );
-                    }
+                if !self.retain_if_present(attrs) {
+                    item = parse_quote!(
+                        use {};
+                    );
                 }
             }
             _ => {}
@@ -248,20 +407,84 @@ impl Fold for VersionFilter {
     }
 }
 
+/// One element of the comma-separated list passed to `#[versioned(..)]`: either
+/// a version string such as `"1.4"`, or the `derive_upgrades` flag that opts
+/// into generating `From` impls between adjacent generated modules.
+enum VersionedArg {
+    Version(syn::LitStr),
+    DeriveUpgrades,
+}
+
+impl syn::parse::Parse for VersionedArg {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::LitStr) {
+            return Ok(Self::Version(input.parse()?));
+        }
+
+        let ident = input.parse::<syn::Ident>()?;
+        if ident == "derive_upgrades" {
+            Ok(Self::DeriveUpgrades)
+        } else {
+            Err(Error::new(
+                ident.span(),
+                "expected a version string literal or `derive_upgrades`",
+            ))
+        }
+    }
+}
+
+/// Sets the `rename` key on `attrs`' `#[serde(..)]` attribute to `rename`,
+/// inserting the attribute if absent, so a version-specific
+/// `#[versioned("1.4", rename = "..")]` can override the serialized name for
+/// that generated module. Other `#[serde(..)]` meta items (e.g.
+/// `skip_serializing_if`) are left untouched.
+fn apply_rename(attrs: &mut Vec<syn::Attribute>, rename: syn::LitStr) {
+    let Some(existing) = attrs.iter_mut().find(|attr| attr.path().is_ident("serde")) else {
+        attrs.push(parse_quote!(#[serde(rename = #rename)]));
+        return;
+    };
+
+    let mut metas = existing
+        .parse_args_with(Punctuated::<syn::Meta, Comma>::parse_terminated)
+        .unwrap_or_default();
+
+    match metas.iter_mut().find(|meta| meta.path().is_ident("rename")) {
+        Some(slot) => *slot = parse_quote!(rename = #rename),
+        None => metas.push(parse_quote!(rename = #rename)),
+    }
+
+    *existing = parse_quote!(#[serde(#metas)]);
+}
+
+// `helper` operates on `proc_macro2::TokenStream` rather than `proc_macro::TokenStream`
+// so that it can be called directly from tests, without going through an actual
+// macro invocation (which `proc_macro::TokenStream` only supports).
 fn helper(
-    input: TokenStream,
-    annotated_item: TokenStream,
+    input: proc_macro2::TokenStream,
+    annotated_item: proc_macro2::TokenStream,
 ) -> syn::Result<proc_macro2::TokenStream> {
     // This parses the module being annotated by the `#[versioned(..)]` attribute.
-    let module = syn::parse::<syn::ItemMod>(annotated_item)?;
+    let module = syn::parse2::<syn::ItemMod>(annotated_item)?;
 
     // This parses the versions passed to the attribute, e.g. the `"1.3"`
-    // and `"1.4"`in `#[versioned("1.3", "1.4")]
-    let versions =
-        syn::parse::Parser::parse(Punctuated::<syn::LitStr, Comma>::parse_terminated, input)?
-            .into_iter()
-            .map(|s| s.value().parse().map_err(|err| Error::new(s.span(), err)))
-            .collect::<syn::Result<Vec<Version>>>()?;
+    // and `"1.4"`in `#[versioned("1.3", "1.4")]`, plus the optional trailing
+    // `derive_upgrades` flag.
+    let args =
+        syn::parse::Parser::parse2(Punctuated::<VersionedArg, Comma>::parse_terminated, input)?;
+
+    let mut versions: Vec<Version> = Vec::new();
+    let mut derive_upgrades = false;
+
+    for arg in args {
+        match arg {
+            VersionedArg::Version(lit) => versions.push(
+                lit.value()
+                    .parse()
+                    .map_err(|err| Error::new(lit.span(), err))?,
+            ),
+            VersionedArg::DeriveUpgrades => derive_upgrades = true,
+        }
+    }
 
     let content = module
         .content
@@ -269,6 +492,7 @@ fn helper(
         .ok_or_else(|| Error::new(module.ident.span(), "found module without content"))?;
 
     let mut tokens = proc_macro2::TokenStream::new();
+    let mut generated_modules: Vec<(syn::Ident, Vec<Item>)> = Vec::new();
 
     for version in versions {
         let mod_vis = &module.vis;
@@ -294,15 +518,213 @@ fn helper(
              #mod_vis mod #mod_ident {
                 #(#folded_items)*
             }
-        })
+        });
+
+        generated_modules.push((mod_ident, folded_items));
+    }
+
+    if derive_upgrades {
+        for pair in generated_modules.windows(2) {
+            let [(from_ident, from_items), (to_ident, to_items)] = pair else {
+                unreachable!("windows(2) always yields slices of length 2")
+            };
+
+            tokens.extend(derive_upgrade_impls(
+                from_ident, from_items, to_ident, to_items,
+            ));
+        }
     }
 
     Ok(tokens)
 }
 
+/// Emits `impl From<from_mod::T> for to_mod::T` for every struct with named
+/// fields or enum present in both `from_items` and `to_items`. Tuple and unit
+/// structs get no impl.
+fn derive_upgrade_impls(
+    from_mod: &syn::Ident,
+    from_items: &[Item],
+    to_mod: &syn::Ident,
+    to_items: &[Item],
+) -> proc_macro2::TokenStream {
+    let mut tokens = proc_macro2::TokenStream::new();
+
+    for from_item in from_items {
+        match from_item {
+            Item::Struct(from_struct) => {
+                let to_struct = to_items.iter().find_map(|item| match item {
+                    Item::Struct(s) if s.ident == from_struct.ident => Some(s),
+                    _ => None,
+                });
+
+                if let Some(to_struct) = to_struct {
+                    tokens.extend(struct_upgrade_impl(
+                        from_mod,
+                        to_mod,
+                        from_struct,
+                        to_struct,
+                    ));
+                }
+            }
+            Item::Enum(from_enum) => {
+                let to_enum = to_items.iter().find_map(|item| match item {
+                    Item::Enum(e) if e.ident == from_enum.ident => Some(e),
+                    _ => None,
+                });
+
+                if let Some(to_enum) = to_enum {
+                    tokens.extend(enum_upgrade_impl(from_mod, to_mod, from_enum, to_enum));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+/// Builds an upgrade impl for a pair of same-named structs, mapping fields by
+/// name and defaulting fields that only exist in `to`.
+fn struct_upgrade_impl(
+    from_mod: &syn::Ident,
+    to_mod: &syn::Ident,
+    from: &syn::ItemStruct,
+    to: &syn::ItemStruct,
+) -> proc_macro2::TokenStream {
+    let ident = &to.ident;
+
+    let (syn::Fields::Named(from_fields), syn::Fields::Named(to_fields)) =
+        (&from.fields, &to.fields)
+    else {
+        // Tuple and unit structs don't appear in this codebase's `#[versioned]`
+        // modules; skip them rather than guessing at a mapping.
+        return proc_macro2::TokenStream::new();
+    };
+
+    let from_field_names: HashSet<_> = from_fields
+        .named
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .collect();
+
+    let field_inits = to_fields.named.iter().map(|field| {
+        let field_ident = field.ident.as_ref().expect("named field has an ident");
+        if from_field_names.contains(field_ident) {
+            quote! { #field_ident: value.#field_ident }
+        } else {
+            quote! { #field_ident: ::core::default::Default::default() }
+        }
+    });
+
+    quote! {
+        impl ::core::convert::From<#from_mod::#ident> for #to_mod::#ident {
+            fn from(value: #from_mod::#ident) -> Self {
+                Self {
+                    #(#field_inits),*
+                }
+            }
+        }
+    }
+}
+
+/// Builds an upgrade impl for a pair of same-named enums, mapping shared
+/// variants straight through. If a variant from `from` has no equivalent in
+/// `to`, the generated `match` falls back to `Default::default()`, so `to`
+/// must implement `Default` in that case.
+fn enum_upgrade_impl(
+    from_mod: &syn::Ident,
+    to_mod: &syn::Ident,
+    from: &syn::ItemEnum,
+    to: &syn::ItemEnum,
+) -> proc_macro2::TokenStream {
+    let ident = &to.ident;
+
+    let to_variants: HashMap<_, _> = to.variants.iter().map(|v| (&v.ident, v)).collect();
+
+    let mut needs_fallback = false;
+    let mut arms = Vec::new();
+
+    for variant in &from.variants {
+        let Some(&to_variant) = to_variants.get(&variant.ident) else {
+            needs_fallback = true;
+            continue;
+        };
+
+        let variant_ident = &variant.ident;
+
+        let arm = match (&variant.fields, &to_variant.fields) {
+            (syn::Fields::Unit, syn::Fields::Unit) => quote! {
+                #from_mod::#ident::#variant_ident => #to_mod::#ident::#variant_ident
+            },
+            (syn::Fields::Unnamed(from_fields), syn::Fields::Unnamed(to_fields))
+                if from_fields.unnamed.len() == to_fields.unnamed.len() =>
+            {
+                let bindings: Vec<_> = (0..from_fields.unnamed.len())
+                    .map(|i| syn::Ident::new(&format!("field_{i}"), variant_ident.span()))
+                    .collect();
+
+                quote! {
+                    #from_mod::#ident::#variant_ident(#(#bindings),*) =>
+                        #to_mod::#ident::#variant_ident(#(#bindings),*)
+                }
+            }
+            (syn::Fields::Named(from_fields), syn::Fields::Named(to_fields)) => {
+                let from_names: HashSet<_> = from_fields
+                    .named
+                    .iter()
+                    .filter_map(|field| field.ident.as_ref())
+                    .collect();
+                let to_names: HashSet<_> = to_fields
+                    .named
+                    .iter()
+                    .filter_map(|field| field.ident.as_ref())
+                    .collect();
+
+                if from_names != to_names {
+                    needs_fallback = true;
+                    continue;
+                }
+
+                let names: Vec<_> = to_fields
+                    .named
+                    .iter()
+                    .filter_map(|field| field.ident.as_ref())
+                    .collect();
+
+                quote! {
+                    #from_mod::#ident::#variant_ident { #(#names),* } =>
+                        #to_mod::#ident::#variant_ident { #(#names),* }
+                }
+            }
+            _ => {
+                // Field shape changed between versions; fall back rather than
+                // guessing at a mapping.
+                needs_fallback = true;
+                continue;
+            }
+        };
+
+        arms.push(arm);
+    }
+
+    if needs_fallback {
+        arms.push(quote! { _ => ::core::default::Default::default() });
+    }
+
+    quote! {
+        impl ::core::convert::From<#from_mod::#ident> for #to_mod::#ident {
+            fn from(value: #from_mod::#ident) -> Self {
+                match value {
+                    #(#arms),*
+                }
+            }
+        }
+    }
+}
+
 #[proc_macro_attribute]
 pub fn versioned(input: TokenStream, annotated_item: TokenStream) -> TokenStream {
-    match helper(input, annotated_item) {
+    match helper(input.into(), annotated_item.into()) {
         Ok(tokens) => tokens,
         Err(err) => Error::new(
             err.span(),
@@ -312,3 +734,327 @@ pub fn versioned(input: TokenStream, annotated_item: TokenStream) -> TokenStream
     }
     .into()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn version_req_parses_exact() {
+        let req: VersionReq = "1.4".parse().unwrap();
+        assert!(req.matches(&"1.4".parse().unwrap()));
+        assert!(!req.matches(&"1.3".parse().unwrap()));
+        assert!(!req.matches(&"1.5".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_parses_at_least() {
+        let req: VersionReq = ">=1.4".parse().unwrap();
+        assert!(!req.matches(&"1.3".parse().unwrap()));
+        assert!(req.matches(&"1.4".parse().unwrap()));
+        assert!(req.matches(&"1.5".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_parses_at_most() {
+        let req: VersionReq = "<=1.4".parse().unwrap();
+        assert!(req.matches(&"1.3".parse().unwrap()));
+        assert!(req.matches(&"1.4".parse().unwrap()));
+        assert!(!req.matches(&"1.5".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_parses_greater_than() {
+        let req: VersionReq = ">1.4".parse().unwrap();
+        assert!(!req.matches(&"1.4".parse().unwrap()));
+        assert!(req.matches(&"1.5".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_parses_less_than() {
+        let req: VersionReq = "<1.4".parse().unwrap();
+        assert!(req.matches(&"1.3".parse().unwrap()));
+        assert!(!req.matches(&"1.4".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_parses_inclusive_range() {
+        let req: VersionReq = "1.3..=1.6".parse().unwrap();
+        assert!(!req.matches(&"1.2".parse().unwrap()));
+        assert!(req.matches(&"1.3".parse().unwrap()));
+        assert!(req.matches(&"1.6".parse().unwrap()));
+        assert!(!req.matches(&"1.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_parses_exclusive_range() {
+        let req: VersionReq = "1.3..1.6".parse().unwrap();
+        assert!(req.matches(&"1.3".parse().unwrap()));
+        assert!(req.matches(&"1.5".parse().unwrap()));
+        assert!(!req.matches(&"1.6".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_empty_range_matches_nothing() {
+        let req: VersionReq = "1.3..1.3".parse().unwrap();
+        assert!(!req.matches(&"1.2".parse().unwrap()));
+        assert!(!req.matches(&"1.3".parse().unwrap()));
+        assert!(!req.matches(&"1.4".parse().unwrap()));
+    }
+
+    #[test]
+    fn version_req_rejects_malformed_bound() {
+        assert!("1.3..".parse::<VersionReq>().is_err());
+        assert!(">=".parse::<VersionReq>().is_err());
+        assert!("not-a-version".parse::<VersionReq>().is_err());
+    }
+
+    /// Runs the `#[versioned(..)]` expansion directly (bypassing the real macro
+    /// invocation) and parses the result back into a `syn::File` for inspection.
+    fn expand(args: proc_macro2::TokenStream, module: proc_macro2::TokenStream) -> syn::File {
+        let tokens = helper(args, module).expect("expansion to succeed");
+        syn::parse2(tokens).expect("generated code to parse as a file")
+    }
+
+    fn find_mod<'a>(file: &'a syn::File, name: &str) -> &'a [Item] {
+        file.items
+            .iter()
+            .find_map(|item| match item {
+                Item::Mod(m) if m.ident == name => {
+                    m.content.as_ref().map(|(_, items)| items.as_slice())
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no `mod {name}` in generated code"))
+    }
+
+    fn find_enum<'a>(items: &'a [Item], name: &str) -> &'a syn::ItemEnum {
+        items
+            .iter()
+            .find_map(|item| match item {
+                Item::Enum(e) if e.ident == name => Some(e),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no `enum {name}` in generated module"))
+    }
+
+    fn variant_names(item: &syn::ItemEnum) -> Vec<String> {
+        item.variants.iter().map(|v| v.ident.to_string()).collect()
+    }
+
+    #[test]
+    fn filters_enum_variants_by_version() {
+        let file = expand(
+            quote! { "1.4", "1.5" },
+            quote! {
+                mod example {
+                    pub enum Algorithm {
+                        Md5,
+                        #[versioned("1.5")]
+                        Sha3_256,
+                    }
+                }
+            },
+        );
+
+        assert_eq!(
+            variant_names(find_enum(find_mod(&file, "v1_4"), "Algorithm")),
+            vec!["Md5"]
+        );
+        assert_eq!(
+            variant_names(find_enum(find_mod(&file, "v1_5"), "Algorithm")),
+            vec!["Md5", "Sha3_256"]
+        );
+    }
+
+    fn find_struct<'a>(items: &'a [Item], name: &str) -> &'a syn::ItemStruct {
+        items
+            .iter()
+            .find_map(|item| match item {
+                Item::Struct(s) if s.ident == name => Some(s),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no `struct {name}` in generated module"))
+    }
+
+    #[test]
+    fn derives_upgrade_impls_between_adjacent_versions() {
+        let file = expand(
+            quote! { "1.3", "1.4", derive_upgrades },
+            quote! {
+                mod example {
+                    pub struct Widget {
+                        pub name: String,
+                        #[versioned(">=1.4")]
+                        pub note: Option<String>,
+                    }
+                }
+            },
+        );
+
+        // The field gated on ">=1.4" is dropped from v1_3, so both modules still
+        // have a `Widget`, just with different fields.
+        assert_eq!(
+            find_struct(find_mod(&file, "v1_3"), "Widget").fields.len(),
+            1
+        );
+        assert_eq!(
+            find_struct(find_mod(&file, "v1_4"), "Widget").fields.len(),
+            2
+        );
+
+        let upgrade_impl = file
+            .items
+            .iter()
+            .find_map(|item| match item {
+                Item::Impl(i) => Some(i),
+                _ => None,
+            })
+            .expect("a generated `impl From<..> for ..`");
+
+        let impl_tokens = quote!(#upgrade_impl).to_string();
+        assert!(impl_tokens.contains("v1_3 :: Widget"));
+        assert!(impl_tokens.contains("v1_4 :: Widget"));
+        assert!(impl_tokens.contains("name : value . name"));
+        assert!(impl_tokens.contains("note : :: core :: default :: Default :: default ()"));
+    }
+
+    fn named_field<'a>(item: &'a syn::ItemStruct, name: &str) -> &'a syn::Field {
+        find_named_field(item, name)
+            .unwrap_or_else(|| panic!("no field `{name}` on `{}`", item.ident))
+    }
+
+    fn find_named_field<'a>(item: &'a syn::ItemStruct, name: &str) -> Option<&'a syn::Field> {
+        let syn::Fields::Named(fields) = &item.fields else {
+            panic!("`{}` has no named fields", item.ident);
+        };
+
+        fields
+            .named
+            .iter()
+            .find(|field| field.ident.as_ref().is_some_and(|ident| ident == name))
+    }
+
+    fn serde_rename(field: &syn::Field) -> Option<String> {
+        let serde_attr = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("serde"))?;
+
+        let metas = serde_attr
+            .parse_args_with(Punctuated::<syn::Meta, Comma>::parse_terminated)
+            .expect("parseable `#[serde(..)]` args");
+
+        metas.iter().find_map(|meta| match meta {
+            syn::Meta::NameValue(name_value) if name_value.path.is_ident("rename") => {
+                let Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(lit),
+                    ..
+                }) = &name_value.value
+                else {
+                    panic!("`rename` is not a string literal");
+                };
+
+                Some(lit.value())
+            }
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn applies_versioned_rename() {
+        let file = expand(
+            quote! { "1.3", "1.4" },
+            quote! {
+                mod example {
+                    pub struct Volume {
+                        #[versioned(">=1.3", rename = "sizeAllocated")]
+                        #[serde(rename = "size", skip_serializing_if = "Option::is_none")]
+                        pub size_allocated: Option<String>,
+                    }
+                }
+            },
+        );
+
+        for mod_name in ["v1_3", "v1_4"] {
+            let field = named_field(
+                find_struct(find_mod(&file, mod_name), "Volume"),
+                "size_allocated",
+            );
+
+            let serde_attr = field
+                .attrs
+                .iter()
+                .find(|attr| attr.path().is_ident("serde"))
+                .expect("a `#[serde(..)]` attribute");
+
+            let attr_tokens = quote!(#serde_attr).to_string();
+            assert!(attr_tokens.contains("rename = \"sizeAllocated\""));
+            // The unrelated serde meta item on the original attribute must survive.
+            assert!(attr_tokens.contains("skip_serializing_if"));
+        }
+    }
+
+    #[test]
+    fn rename_only_attribute_does_not_gate_presence() {
+        // A single `#[versioned(.., rename = ..)]` attribute whose requirement
+        // only matches some of the declared versions must still keep the
+        // field present everywhere -- it only switches the serialized name
+        // from the cutoff version onward.
+        let file = expand(
+            quote! { "1.3", "1.4" },
+            quote! {
+                mod example {
+                    pub struct Volume {
+                        #[versioned(">=1.4", rename = "sizeAllocated")]
+                        #[serde(rename = "size")]
+                        pub size_allocated: Option<String>,
+                    }
+                }
+            },
+        );
+
+        let v1_3 = find_struct(find_mod(&file, "v1_3"), "Volume");
+        let v1_3_field = named_field(v1_3, "size_allocated");
+        assert_eq!(serde_rename(v1_3_field).as_deref(), Some("size"));
+
+        let v1_4 = find_struct(find_mod(&file, "v1_4"), "Volume");
+        let v1_4_field = named_field(v1_4, "size_allocated");
+        assert_eq!(serde_rename(v1_4_field).as_deref(), Some("sizeAllocated"));
+    }
+
+    #[test]
+    fn multiple_versioned_attributes_or_together() {
+        // Stacking two plain (non-rename) `#[versioned(..)]` attributes on one
+        // field must OR their requirements for presence, not have the second
+        // silently replace the first.
+        let file = expand(
+            quote! { "1.3", "1.4", "1.5" },
+            quote! {
+                mod example {
+                    pub struct Volume {
+                        #[versioned("1.3")]
+                        #[versioned("1.5")]
+                        pub size_allocated: Option<String>,
+                    }
+                }
+            },
+        );
+
+        assert!(find_named_field(
+            find_struct(find_mod(&file, "v1_3"), "Volume"),
+            "size_allocated"
+        )
+        .is_some());
+        assert!(find_named_field(
+            find_struct(find_mod(&file, "v1_4"), "Volume"),
+            "size_allocated"
+        )
+        .is_none());
+        assert!(find_named_field(
+            find_struct(find_mod(&file, "v1_5"), "Volume"),
+            "size_allocated"
+        )
+        .is_some());
+    }
+}